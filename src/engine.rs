@@ -1,46 +1,211 @@
 use pleco::{Board, BitMove, Player, PieceType, SQ};
-use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 pub const MAX_DEPTH: u8 = 5;
 pub const INFINITY: i32 = 1000000;
 pub const MAX_PLY: usize = 64;
 
+// Number of TT entries, kept a power of two so probing is a mask instead of
+// a modulo. ~1M entries is a few tens of MB, allocated once and kept for
+// the whole game, with the aging policy in `store` recycling stale slots.
+const TT_SIZE: usize = 1 << 20;
+
 #[derive(Clone, Copy)]
 pub struct TTEntry {
+    pub key: u64,
     pub score: i32,
     pub depth: u8,
     pub flag: u8, // 0: exact, 1: lower, 2: upper
+    pub best_move: BitMove,
+    pub age: u8,
+}
+
+impl TTEntry {
+    const EMPTY: TTEntry = TTEntry {
+        key: 0,
+        score: 0,
+        depth: 0,
+        flag: 0,
+        best_move: BitMove::null(),
+        age: 0,
+    };
+}
+
+/// Result of a TT lookup: a usable score (if the stored depth/bound allow a
+/// cutoff) and, independently, the best move from the last time this
+/// position was searched (if any), for move ordering.
+pub struct TTProbe {
+    pub score: Option<i32>,
+    pub best_move: Option<BitMove>,
 }
 
+/// Fixed-size, key-verified transposition table. Unlike a `HashMap`, slots
+/// are never allocated mid-search: every probe/store is a single indexed
+/// lock-and-read/write into a preallocated `Vec`, and a stored 64-bit key
+/// rules out returning another position's score on an index collision.
+///
+/// Each bucket is its own `Mutex` rather than one lock over the whole table,
+/// so Lazy SMP worker threads sharing a `CustomTT` by reference only
+/// contend when two threads hash to the same bucket at the same instant.
 pub struct CustomTT {
-    pub table: HashMap<u64, TTEntry>,
+    table: Vec<Mutex<TTEntry>>,
+    mask: u64,
+    generation: AtomicU8,
 }
 
 impl CustomTT {
     pub fn new() -> Self {
-        CustomTT { table: HashMap::new() }
+        CustomTT {
+            table: (0..TT_SIZE).map(|_| Mutex::new(TTEntry::EMPTY)).collect(),
+            mask: (TT_SIZE - 1) as u64,
+            generation: AtomicU8::new(0),
+        }
     }
 
-    pub fn probe(&mut self, hash: u64, depth: u8, alpha: i32, beta: i32) -> Option<i32> {
-        if let Some(entry) = self.table.get(&hash) {
-            if entry.depth >= depth {
-                match entry.flag {
-                    0 => Some(entry.score),
-                    1 => if entry.score >= beta { Some(entry.score) } else { None },
-                    2 => if entry.score <= alpha { Some(entry.score) } else { None }
-                    _ => None,
-                }
-            } else {
-                None
+    fn index(&self, hash: u64) -> usize {
+        (hash & self.mask) as usize
+    }
+
+    /// Bumped once per `get_best_move` call so `store` can tell entries left
+    /// over from a previous search apart from ones written by this one.
+    pub fn new_search(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, hash: u64, depth: u8, alpha: i32, beta: i32) -> TTProbe {
+        let entry = *self.table[self.index(hash)].lock().unwrap();
+        if entry.key != hash {
+            return TTProbe { score: None, best_move: None };
+        }
+
+        let best_move = if entry.best_move.is_null() { None } else { Some(entry.best_move) };
+        let score = if entry.depth >= depth {
+            match entry.flag {
+                0 => Some(entry.score),
+                1 if entry.score >= beta => Some(entry.score),
+                2 if entry.score <= alpha => Some(entry.score),
+                _ => None,
             }
         } else {
             None
+        };
+
+        TTProbe { score, best_move }
+    }
+
+    pub fn store(&self, hash: u64, depth: u8, score: i32, flag: u8, best_move: BitMove) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let mut entry = self.table[self.index(hash)].lock().unwrap();
+        let is_empty = entry.key == 0 && entry.depth == 0 && entry.flag == 0;
+        let should_replace = is_empty || entry.age != generation || depth >= entry.depth;
+        if should_replace {
+            *entry = TTEntry { key: hash, score, depth, flag, best_move, age: generation };
+        }
+    }
+}
+
+/// How often (in nodes) the search checks the clock. Checking every node
+/// would dominate runtime with syscalls; every couple thousand is enough
+/// resolution for a deadline to still feel responsive.
+const TIME_CHECK_INTERVAL: u64 = 2048;
+
+/// Signals that the search ran out of its time budget mid-tree. The root
+/// catches this and falls back to the best move from the last fully
+/// completed iteration instead of returning a half-searched score.
+pub struct SearchTimeout;
+
+/// Per-call search state threaded through `minimax`/`quiescence`: a node
+/// counter for clock checks, the stop conditions (a wall-clock deadline and
+/// a shared flag other threads can set, e.g. on a UCI `stop`), and the
+/// zobrist-key history of the game so far plus every move made along the
+/// current search path, for repetition detection. `thread_id` is 0 for the
+/// main search thread and >0 for Lazy SMP helpers, which use it to
+/// deliberately diversify their move ordering from the main thread's.
+pub struct SearchContext {
+    pub deadline: Option<Instant>,
+    pub stop: Arc<AtomicBool>,
+    pub nodes: u64,
+    pub history: Vec<u64>,
+    pub thread_id: usize,
+    pub killers: [[BitMove; 2]; MAX_PLY],
+    pub history_heuristic: [[i32; 64]; 6],
+}
+
+impl SearchContext {
+    pub fn new(deadline: Option<Instant>, stop: Arc<AtomicBool>, history: Vec<u64>) -> Self {
+        SearchContext {
+            deadline,
+            stop,
+            nodes: 0,
+            history,
+            thread_id: 0,
+            killers: [[BitMove::null(); 2]; MAX_PLY],
+            history_heuristic: [[0i32; 64]; 6],
+        }
+    }
+
+    fn unlimited(history: Vec<u64>) -> Self {
+        SearchContext::new(None, Arc::new(AtomicBool::new(false)), history)
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        if self.stop.load(Ordering::Relaxed) {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+
+    #[inline]
+    fn tick(&mut self) -> Result<(), SearchTimeout> {
+        self.nodes += 1;
+        if self.nodes % TIME_CHECK_INTERVAL == 0 && self.should_stop() {
+            return Err(SearchTimeout);
         }
+        Ok(())
     }
+}
+
+/// UCI-style time allocation: spend a slice of what's left on the clock plus
+/// half the increment, the way most simple engines budget per move.
+pub fn time_budget_ms(time_left_ms: u64, increment_ms: u64) -> u64 {
+    time_left_ms / 30 + increment_ms / 2
+}
 
-    pub fn store(&mut self, hash: u64, depth: u8, score: i32, flag: u8) {
-        self.table.insert(hash, TTEntry { score, depth, flag });
+/// True if `history.last()` (the position currently being searched) already
+/// occurred earlier in `history`, within the last `halfmove_clock` plies.
+/// Only positions with the same side to move can repeat, hence the step of
+/// 2; a capture or pawn move beyond the fifty-move horizon makes the
+/// position unreachable again, so there's no point scanning further back.
+fn is_repeated_position(history: &[u64], halfmove_clock: u16) -> bool {
+    let len = history.len();
+    if len == 0 {
+        return false;
+    }
+    let current = history[len - 1];
+    let max_offset = (halfmove_clock as usize).min(len - 1);
+    let mut offset = 2;
+    while offset <= max_offset {
+        if history[len - 1 - offset] == current {
+            return true;
+        }
+        offset += 2;
     }
+    false
+}
+
+/// True if `current_hash` has already occurred at least twice in `history`,
+/// i.e. playing into it now would make it the position's third occurrence.
+/// Used by the game-playing root to claim a draw outright instead of
+/// searching a position it's about to (or already did) repeat into.
+pub fn has_occurred_twice(history: &[u64], current_hash: u64) -> bool {
+    history.iter().filter(|&&h| h == current_hash).count() >= 2
 }
 
 // Helper functions
@@ -89,13 +254,17 @@ pub fn bitmove_to_san(board: &Board, mv: BitMove) -> String {
         return "O-O-O".to_string();
     }
 
-    // Disambiguation (basic)
+    // Disambiguation (basic). Sibling promotion moves to the same square
+    // (=N/=B/=R/=Q) aren't an ambiguity the file letter needs to resolve -
+    // the promotion suffix already does that - so skip it for promotions.
     let mut disamb = String::new();
     let moves = board.generate_moves();
     let mut count_same = 0;
-    for other_mv in moves.iter() {
-        if board.legal_move(*other_mv) && other_mv.get_dest() == to_sq && board.moved_piece(*other_mv).type_of() == moved_pt && *other_mv != mv {
-            count_same += 1;
+    if !mv.is_promo() {
+        for other_mv in moves.iter() {
+            if board.legal_move(*other_mv) && other_mv.get_dest() == to_sq && board.moved_piece(*other_mv).type_of() == moved_pt && *other_mv != mv {
+                count_same += 1;
+            }
         }
     }
     if count_same > 0 {
@@ -119,12 +288,12 @@ pub fn bitmove_to_san(board: &Board, mv: BitMove) -> String {
     san.push(file_char(to_file_idx));
     san.push(rank_char(to_rank_idx));
 
-    // Promotion (basic: pawn to last rank, default Q)
-    let is_white = board.turn() == Player::White;
-    let last_rank_idx = if is_white { 7u8 } else { 0u8 };
-    if moved_pt == PieceType::P && to_rank_idx == last_rank_idx {
+    // Promotion: read the piece the move actually promotes to, so
+    // underpromotions (e8=N, exd8=R, ...) round-trip instead of every pawn
+    // move to the last rank being reported as `=Q`.
+    if mv.is_promo() {
         san.push('=');
-        san.push('Q');
+        san.push(piece_char(mv.promo_piece()));
     }
 
     // Check suffix
@@ -142,7 +311,22 @@ pub fn bitmove_to_san(board: &Board, mv: BitMove) -> String {
 
 pub fn san_to_bitmove(board: &Board, san: &str) -> Result<BitMove, String> {
     let lowered = san.trim().to_lowercase();
-    let clean_san = lowered.trim_end_matches(|c| c == '+' || c == '#');
+    let mut clean_san = lowered.trim_end_matches(|c| c == '+' || c == '#').to_string();
+
+    // Some sources write promotions as a bare trailing piece letter (e8n,
+    // exd8r, ...) instead of the `=`-suffixed form `bitmove_to_san` always
+    // generates (e8=n). Normalize that shorthand to the `=` form up front so
+    // the general match below (which compares against generated SAN) still
+    // finds it.
+    let bytes = clean_san.as_bytes();
+    if !clean_san.contains('=') && bytes.len() >= 2 {
+        let last = bytes[bytes.len() - 1] as char;
+        let prev = bytes[bytes.len() - 2] as char;
+        if matches!(last, 'n' | 'b' | 'r' | 'q') && prev.is_ascii_digit() {
+            clean_san.insert(clean_san.len() - 1, '=');
+        }
+    }
+    let clean_san = clean_san.as_str();
 
     // Castling
     let is_white = board.turn() == Player::White;
@@ -161,11 +345,14 @@ pub fn san_to_bitmove(board: &Board, san: &str) -> Result<BitMove, String> {
         }
     }
 
-    // General match
+    // General match. The generated SAN carries its own +/# check suffix,
+    // which callers (PGN without annotations, bare UCI-ish input) may not
+    // include, so compare both sides with it stripped.
     for mv in moves.iter() {
         if board.legal_move(*mv) {
             let mv_san = bitmove_to_san(board, *mv).to_lowercase();
-            if mv_san == clean_san {
+            let mv_san_clean = mv_san.trim_end_matches(|c| c == '+' || c == '#');
+            if mv_san_clean == clean_san {
                 return Ok(*mv);
             }
         }
@@ -174,15 +361,65 @@ pub fn san_to_bitmove(board: &Board, san: &str) -> Result<BitMove, String> {
     Err(format!("No matching move for SAN: {}", san))
 }
 
+pub fn bitmove_to_uci(mv: BitMove) -> String {
+    if mv.is_null() {
+        return "0000".to_string();
+    }
+
+    let from_sq = mv.get_src();
+    let to_sq = mv.get_dest();
+
+    let mut uci = String::new();
+    uci.push(file_char(pleco::core::file_idx_of_sq(from_sq.0)));
+    uci.push(rank_char(pleco::core::rank_idx_of_sq(from_sq.0)));
+    uci.push(file_char(pleco::core::file_idx_of_sq(to_sq.0)));
+    uci.push(rank_char(pleco::core::rank_idx_of_sq(to_sq.0)));
+
+    if mv.is_promo() {
+        let promo_char = match mv.promo_piece() {
+            PieceType::N => 'n',
+            PieceType::B => 'b',
+            PieceType::R => 'r',
+            _ => 'q',
+        };
+        uci.push(promo_char);
+    }
+
+    uci
+}
+
+pub fn uci_to_bitmove(board: &Board, uci_move: &str) -> Result<BitMove, String> {
+    let lowered = uci_move.trim().to_lowercase();
+    let moves = board.generate_pseudolegal_moves();
+    for mv in moves.iter() {
+        if board.legal_move(*mv) && bitmove_to_uci(*mv) == lowered {
+            return Ok(*mv);
+        }
+    }
+    Err(format!("No matching move for UCI notation: {}", uci_move))
+}
+
+// Phase weights used to blend the midgame and endgame PSQTs below. Knights
+// and bishops count for 1, rooks for 2, queens for 4; a full board of
+// non-pawn material sums to TOTAL_PHASE.
+const KNIGHT_PHASE: i32 = 1;
+const BISHOP_PHASE: i32 = 1;
+const ROOK_PHASE: i32 = 2;
+const QUEEN_PHASE: i32 = 4;
+const TOTAL_PHASE: i32 = KNIGHT_PHASE * 4 + BISHOP_PHASE * 4 + ROOK_PHASE * 4 + QUEEN_PHASE * 2;
+
 // Fix evaluate_board parens
 pub fn evaluate_board(board: &Board) -> i32 {
-    let mut score = 0i32;
+    let mut mg_score = 0i32;
+    let mut eg_score = 0i32;
+    let mut phase = 0i32;
+
     for i in 0u8..64 {
         let sq = SQ(i);
         let p = board.piece_at_sq(sq);
         if let Some(pl) = p.player() {
             let pt = p.type_of();
-            let material_value = match pt {
+            let material_value: i32 = match pt {
                 PieceType::P => 100,
                 PieceType::N => 320,
                 PieceType::B => 330,
@@ -191,24 +428,41 @@ pub fn evaluate_board(board: &Board) -> i32 {
                 PieceType::K => 20000,
                 _ => 0,
             };
-            let psqt_index = if pl == Player::White { sq.0 as usize } else { 63 - sq.0 as usize };
-            let psqt_value = match pt {
-                PieceType::P => PSQT_PAWN_MG[psqt_index],
-                PieceType::N => PSQT_KNIGHT_MG[psqt_index],
-                PieceType::B => PSQT_BISHOP_MG[psqt_index],
-                PieceType::R => PSQT_ROOK_MG[psqt_index],
-                PieceType::Q => PSQT_QUEEN_MG[psqt_index],
-                PieceType::K => PSQT_KING_MG[psqt_index],
+            phase += match pt {
+                PieceType::N => KNIGHT_PHASE,
+                PieceType::B => BISHOP_PHASE,
+                PieceType::R => ROOK_PHASE,
+                PieceType::Q => QUEEN_PHASE,
                 _ => 0,
             };
-            let total_value = material_value as i16 + psqt_value;
+
+            let psqt_index = if pl == Player::White { sq.0 as usize } else { 63 - sq.0 as usize };
+            let (psqt_mg, psqt_eg): (i16, i16) = match pt {
+                PieceType::P => (PSQT_PAWN_MG[psqt_index], PSQT_PAWN_EG[psqt_index]),
+                PieceType::N => (PSQT_KNIGHT_MG[psqt_index], PSQT_KNIGHT_EG[psqt_index]),
+                PieceType::B => (PSQT_BISHOP_MG[psqt_index], PSQT_BISHOP_EG[psqt_index]),
+                PieceType::R => (PSQT_ROOK_MG[psqt_index], PSQT_ROOK_EG[psqt_index]),
+                PieceType::Q => (PSQT_QUEEN_MG[psqt_index], PSQT_QUEEN_EG[psqt_index]),
+                PieceType::K => (PSQT_KING_MG[psqt_index], PSQT_KING_EG[psqt_index]),
+                _ => (0, 0),
+            };
+
+            let mg_value = material_value + psqt_mg as i32;
+            let eg_value = material_value + psqt_eg as i32;
+
             if pl == Player::White {
-                score += total_value as i32;
+                mg_score += mg_value;
+                eg_score += eg_value;
             } else {
-                score -= total_value as i32;
+                mg_score -= mg_value;
+                eg_score -= eg_value;
             }
         }
     }
+
+    let phase = phase.min(TOTAL_PHASE);
+    let score = (mg_score * phase + eg_score * (TOTAL_PHASE - phase)) / TOTAL_PHASE;
+
     if board.turn() == Player::White { score } else { -score }
 }
 
@@ -279,11 +533,98 @@ pub const PSQT_KING_MG: [i16; 64] = [
     20, 30, 10, 0, 0, 10, 30, 20,
 ];
 
-static mut KILLERS: [[BitMove; 2]; MAX_PLY] = [[BitMove::null(); 2]; MAX_PLY];
-static mut HISTORY: [[i32; 64]; 6] = [[0i32; 64]; 6];
+// Endgame PSQTs. Pawns push toward promotion instead of holding the start
+// rank, and the king table flips from "stay behind the castled pawns" to
+// "walk to the center" now that there's no middlegame attack to hide from.
+pub const PSQT_PAWN_EG: [i16; 64] = [
+    0, 0, 0, 0, 0, 0, 0, 0,
+    178, 173, 158, 134, 147, 132, 165, 187,
+    94, 100, 85, 67, 56, 53, 82, 84,
+    32, 24, 13, 5, -2, 4, 17, 17,
+    13, 9, -3, -7, -7, -8, 3, -1,
+    4, 7, -6, 1, 0, -5, -1, -8,
+    13, 8, 8, 10, 13, 0, 2, -7,
+    0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+pub const PSQT_KNIGHT_EG: [i16; 64] = [
+    -58, -38, -13, -28, -31, -27, -63, -99,
+    -25, -8, -25, -2, -9, -25, -24, -52,
+    -24, -20, 10, 9, -1, -9, -19, -41,
+    -17, 3, 22, 22, 22, 11, 8, -18,
+    -18, -6, 16, 25, 16, 17, 4, -18,
+    -23, -3, -1, 15, 10, -3, -20, -22,
+    -42, -20, -10, -5, -2, -20, -23, -44,
+    -29, -51, -23, -15, -22, -18, -50, -64,
+];
+
+pub const PSQT_BISHOP_EG: [i16; 64] = [
+    -14, -21, -11, -8, -7, -9, -17, -24,
+    -8, -4, 7, -12, -3, -13, -4, -14,
+    2, -8, 0, -1, -2, 6, 0, 4,
+    -3, 9, 12, 9, 14, 10, 3, 2,
+    -6, 3, 13, 19, 7, 10, -3, -9,
+    -12, -3, 8, 10, 13, 3, -7, -15,
+    -14, -18, -7, -1, 4, -9, -15, -27,
+    -23, -9, -23, -5, -9, -16, -5, -17,
+];
 
-pub fn score_move(m: BitMove, board: &Board) -> i32 {
-    if board.is_capture(m) {
+pub const PSQT_ROOK_EG: [i16; 64] = [
+    13, 10, 18, 15, 12, 12, 8, 5,
+    11, 13, 13, 11, -3, 3, 8, 3,
+    7, 7, 7, 5, 4, -3, -5, -3,
+    4, 3, 13, 1, 2, 1, -1, 2,
+    3, 5, 8, 4, -5, -6, -8, -11,
+    -4, 0, -5, -1, -7, -12, -8, -16,
+    -6, -6, 0, 2, -9, -9, -11, -3,
+    -9, 2, 3, -1, -5, -13, 4, -20,
+];
+
+pub const PSQT_QUEEN_EG: [i16; 64] = [
+    -9, 22, 22, 27, 27, 19, 10, 20,
+    -17, 20, 32, 41, 58, 25, 30, 0,
+    -20, 6, 9, 49, 47, 35, 19, 9,
+    3, 22, 24, 45, 57, 40, 57, 36,
+    -18, 28, 19, 47, 31, 34, 39, 23,
+    -16, -27, 15, 6, 9, 17, 10, 5,
+    -22, -23, -30, -16, -16, -23, -36, -32,
+    -33, -28, -22, -43, -5, -32, -20, -41,
+];
+
+pub const PSQT_KING_EG: [i16; 64] = [
+    -74, -35, -18, -18, -11, 15, 4, -17,
+    -12, 17, 14, 17, 17, 38, 23, 11,
+    10, 17, 23, 15, 20, 45, 44, 13,
+    -8, 22, 24, 27, 26, 33, 26, 3,
+    -18, -4, 21, 24, 27, 23, 9, -11,
+    -19, -3, 11, 21, 23, 16, 7, -9,
+    -27, -11, 4, 13, 14, 4, -5, -17,
+    -53, -34, -21, -11, -28, -14, -24, -43,
+];
+
+fn piece_index(pt: PieceType) -> usize {
+    match pt {
+        PieceType::P => 0,
+        PieceType::N => 1,
+        PieceType::B => 2,
+        PieceType::R => 3,
+        PieceType::Q => 4,
+        PieceType::K => 5,
+        _ => 0,
+    }
+}
+
+/// Killers/history live on `SearchContext` (one per search, reset every call)
+/// rather than in a process-wide global, so Lazy SMP helper threads each keep
+/// their own ordering state instead of racing on a shared `static mut`.
+/// `ply` is the current search ply within `ctx.killers`; `thread_id` nudges a
+/// helper thread's ordering away from the main thread's so the two explore
+/// different subtrees at the same depth.
+pub fn score_move(m: BitMove, board: &Board, tt_move: Option<BitMove>, ctx: &SearchContext, ply: usize) -> i32 {
+    if Some(m) == tt_move {
+        return 20000;
+    }
+    let base = if board.is_capture(m) {
         // MVV-LVA using pleco values
         let victim_pt = board.captured_piece(m);
         let attacker_pt = board.moved_piece(m).type_of();
@@ -306,31 +647,77 @@ pub fn score_move(m: BitMove, board: &Board) -> i32 {
             _ => 0,
         };
         10000 + mvv * 100 - lva
-    } else if m == unsafe { KILLERS[0][0] } || m == unsafe { KILLERS[0][1] } {
-        return 9000;
     } else {
-        let pt_idx = match board.moved_piece(m).type_of() {
-            PieceType::P => 0,
-            PieceType::N => 1,
-            PieceType::B => 2,
-            PieceType::R => 3,
-            PieceType::Q => 4,
-            PieceType::K => 5,
-            _ => 0,
-        };
-        let sq = m.get_dest();
-        unsafe { HISTORY[pt_idx][sq.0 as usize] + 1000 }
+        let killers = ctx.killers[ply.min(MAX_PLY - 1)];
+        if m == killers[0] || m == killers[1] {
+            9000
+        } else {
+            let pt_idx = piece_index(board.moved_piece(m).type_of());
+            let sq = m.get_dest();
+            ctx.history_heuristic[pt_idx][sq.0 as usize] + 1000
+        }
+    };
+
+    if ctx.thread_id == 0 {
+        base
+    } else {
+        // Deterministic per-thread jitter so helper threads diversify their
+        // move order from the main thread's instead of duplicating its work.
+        let jitter = ((m.get_src().0 as i32 * 31 + m.get_dest().0 as i32) * ctx.thread_id as i32) % 64 - 32;
+        base + jitter
     }
 }
 
-pub fn order_moves(moves: &mut Vec<BitMove>, board: &Board) { // Use slice for efficiency
-    moves.sort_by_key(|&m| std::cmp::Reverse(score_move(m, board)));
+pub fn order_moves(moves: &mut Vec<BitMove>, board: &Board, tt_move: Option<BitMove>, ctx: &SearchContext, ply: usize) {
+    moves.sort_by_key(|&m| std::cmp::Reverse(score_move(m, board, tt_move, ctx, ply)));
+}
+
+/// `history` is the zobrist hash of every position that has actually
+/// occurred so far in the game (oldest first, ending with the current
+/// position), used to recognize repetitions the search walks into. `tt`
+/// is owned by the caller and lives across the whole game, so the table's
+/// aging replacement policy actually has something to age against move to
+/// move instead of starting from an empty table every call.
+pub fn get_best_move(board: &mut Board, max_depth: u8, history: &[u64], tt: &CustomTT) -> BitMove {
+    search_root(board, max_depth, 1, tt, &mut SearchContext::unlimited(history.to_vec()))
+}
+
+/// Like `get_best_move`, but controlled by a shared `stop` flag (e.g. set
+/// from a UCI `stop` command) and, optionally, a wall-clock `deadline`
+/// instead of only a depth, searched with `threads` Lazy SMP workers sharing
+/// one TT. `deadline: None` means search to `max_depth` (or forever, for
+/// `go infinite`) until `stop` is set - unlike `get_best_move`, that case is
+/// still interruptible, since `stop` is checked the same way either way.
+pub fn get_best_move_timed(
+    board: &mut Board,
+    max_depth: u8,
+    deadline: Option<Instant>,
+    stop: Arc<AtomicBool>,
+    history: &[u64],
+    threads: usize,
+    tt: &CustomTT,
+) -> BitMove {
+    search_root(board, max_depth, threads, tt, &mut SearchContext::new(deadline, stop, history.to_vec()))
+}
+
+/// Result of one thread's iterative deepening run: the move chosen and the
+/// deepest depth it *fully* completed (0 if none did). Lazy SMP picks the
+/// move from whichever thread completed the deepest iteration.
+struct SearchResult {
+    best_move: BitMove,
+    depth_reached: u8,
 }
 
-pub fn get_best_move(board: &mut Board, max_depth: u8) -> BitMove {
-    let mut tt = CustomTT::new();
+/// Runs iterative deepening to `max_depth` (or until `ctx`'s deadline/stop
+/// fires) on `board`, sharing `tt` with any other threads doing the same.
+/// Only the main thread (`ctx.thread_id == 0`) prints `info` lines; helpers
+/// search silently since a GUI only wants one PV stream.
+fn iterative_deepen(board: &mut Board, max_depth: u8, tt: &CustomTT, ctx: &mut SearchContext) -> SearchResult {
     let maximizing = board.turn() == Player::White;
     let mut best_move = BitMove::null();
+    let mut depth_reached = 0u8;
+    let start = Instant::now();
+    let mut prev_depth_nodes = 0u64;
 
     let moves: Vec<BitMove> = board.generate_pseudolegal_moves()
         .iter()
@@ -338,68 +725,187 @@ pub fn get_best_move(board: &mut Board, max_depth: u8) -> BitMove {
         .cloned()
         .collect();
     if moves.is_empty() {
-        return BitMove::null();
+        return SearchResult { best_move, depth_reached };
     }
 
     for d in 1..=max_depth {
+        let nodes_before = ctx.nodes;
         let mut current_best = BitMove::null();
         let mut current_value = if maximizing { -INFINITY } else { INFINITY };
 
         let mut moves_vec: Vec<BitMove> = moves.iter().cloned().collect();
-        order_moves(&mut moves_vec, board);
+        let tt_move = tt.probe(board.zobrist(), d, -INFINITY, INFINITY).best_move;
+        order_moves(&mut moves_vec, board, tt_move, ctx, 0);
 
+        let mut timed_out = false;
         for m in moves_vec {
             board.apply_move(m);
-            let value = minimax(board, d - 1, -INFINITY, INFINITY, !maximizing, &mut tt);
+            let hash_after = board.zobrist();
+
+            // A move that would make this the position's third occurrence
+            // is an immediate, practical draw claim - no need to search it.
+            let value = if has_occurred_twice(&ctx.history, hash_after) {
+                Ok(0)
+            } else {
+                ctx.history.push(hash_after);
+                let result = minimax(board, d - 1, -INFINITY, INFINITY, !maximizing, tt, ctx);
+                ctx.history.pop();
+                result
+            };
             board.undo_move();
 
+            let value = match value {
+                Ok(v) => v,
+                Err(SearchTimeout) => {
+                    timed_out = true;
+                    break;
+                }
+            };
+
             if (maximizing && value > current_value) || (!maximizing && value < current_value) {
                 current_value = value;
                 current_best = m;
             }
         }
 
-        if current_best.is_null() {
+        // A timeout mid-iteration means this depth never finished; keep the
+        // best move from the last depth that did.
+        if timed_out || current_best.is_null() {
             break;
         }
 
         best_move = current_best;
+        depth_reached = d;
+        let depth_nodes = ctx.nodes - nodes_before;
+
+        if ctx.thread_id == 0 {
+            // `current_value` is White-relative (the minimax maximizing
+            // convention); UCI's `score cp` is relative to the side to move.
+            let uci_score = if board.turn() == Player::White { current_value } else { -current_value };
+            println!(
+                "info depth {} score cp {} nodes {} pv {}",
+                d, uci_score, ctx.nodes, bitmove_to_uci(best_move)
+            );
+            io::stdout().flush().ok();
+        }
+
+        if let Some(deadline) = ctx.deadline {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            // Project the next iteration's cost from the branching-factor
+            // ratio of the last two iterations; don't start a depth we
+            // don't expect to finish within the remaining budget.
+            if prev_depth_nodes > 0 {
+                let branching_factor = depth_nodes as f64 / prev_depth_nodes as f64;
+                let projected_nodes = depth_nodes as f64 * branching_factor;
+                let elapsed_ms = start.elapsed().as_millis().max(1) as f64;
+                let nodes_per_ms = ctx.nodes as f64 / elapsed_ms;
+                let remaining_ms = deadline.saturating_duration_since(now).as_millis() as f64;
+                if nodes_per_ms > 0.0 && projected_nodes / nodes_per_ms > remaining_ms {
+                    break;
+                }
+            }
+        }
+
+        prev_depth_nodes = depth_nodes;
     }
 
-    best_move
+    SearchResult { best_move, depth_reached }
 }
 
-pub fn minimax(board: &mut Board, depth: u8, mut alpha: i32, mut beta: i32, maximizing: bool, tt: &mut CustomTT) -> i32 {
+/// Lazy SMP root: `threads` - 1 helpers run `iterative_deepen` alongside the
+/// main thread, all sharing one `tt` by reference (safe without `Arc` since
+/// `thread::scope` guarantees every spawned thread joins before returning).
+/// Helpers get their own `SearchContext` (and so their own killer/history
+/// tables and a distinct `thread_id` for move-order jitter) but the same
+/// deadline and stop flag, so a `stop`/timeout cuts every thread at once.
+/// The deepest completed iteration wins; the main thread breaks ties so a
+/// single-threaded run is bit-for-bit the same as before.
+fn search_root(board: &mut Board, max_depth: u8, threads: usize, tt: &CustomTT, ctx: &mut SearchContext) -> BitMove {
+    tt.new_search();
+    let threads = threads.max(1);
+
+    if threads == 1 {
+        return iterative_deepen(board, max_depth, tt, ctx).best_move;
+    }
+
+    let mut helper_boards: Vec<Board> = (1..threads).map(|_| board.shallow_clone()).collect();
+    let mut helper_ctxs: Vec<SearchContext> = (1..threads)
+        .map(|id| {
+            let mut helper_ctx = SearchContext::new(ctx.deadline, ctx.stop.clone(), ctx.history.clone());
+            helper_ctx.thread_id = id;
+            helper_ctx
+        })
+        .collect();
+
+    let (main_result, helper_results) = std::thread::scope(|scope| {
+        let handles: Vec<_> = helper_boards
+            .iter_mut()
+            .zip(helper_ctxs.iter_mut())
+            .map(|(helper_board, helper_ctx)| {
+                scope.spawn(move || iterative_deepen(helper_board, max_depth, tt, helper_ctx))
+            })
+            .collect();
+
+        let main_result = iterative_deepen(board, max_depth, tt, ctx);
+        ctx.stop.store(true, Ordering::Relaxed);
+        let helper_results: Vec<SearchResult> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        (main_result, helper_results)
+    });
+
+    let mut best = main_result;
+    for result in helper_results {
+        if result.depth_reached > best.depth_reached {
+            best = result;
+        }
+    }
+    best.best_move
+}
+
+pub fn minimax(board: &mut Board, depth: u8, mut alpha: i32, mut beta: i32, maximizing: bool, tt: &CustomTT, ctx: &mut SearchContext) -> Result<i32, SearchTimeout> {
+    ctx.tick()?;
+
     let hash = board.zobrist();
 
-    if let Some(score) = tt.probe(hash, depth, alpha, beta) {
-        return score;
+    // Repetitions and the fifty-move rule are draws regardless of what the
+    // material/PSQT score says; checked before the TT since the result
+    // depends on the path taken to reach this position, not just the
+    // position itself, so it must never be cached.
+    if board.rule_50() >= 100 || is_repeated_position(&ctx.history, board.rule_50() as u16) {
+        return Ok(0);
+    }
+
+    let probe = tt.probe(hash, depth, alpha, beta);
+    if let Some(score) = probe.score {
+        return Ok(score);
     }
 
     if board.checkmate() {
         let score = if board.turn() == Player::White { -INFINITY } else { INFINITY };
-        tt.store(hash, depth, score, 0);
-        return score;
+        tt.store(hash, depth, score, 0, BitMove::null());
+        return Ok(score);
     }
     if board.stalemate() {
-        tt.store(hash, depth, 0, 0);
-        return 0;
+        tt.store(hash, depth, 0, 0, BitMove::null());
+        return Ok(0);
     }
 
     if depth == 0 {
-        let score = quiescence(board, alpha, beta, tt);
-        tt.store(hash, 0, score, 0);
-        return score;
+        let score = quiescence(board, alpha, beta, tt, ctx)?;
+        tt.store(hash, 0, score, 0, BitMove::null());
+        return Ok(score);
     }
 
     // Null-move pruning
     if depth >= 3 && !maximizing && alpha < beta && !board.in_check() {
         unsafe { board.apply_null_move(); }
-        let null_score = -minimax(board, depth - 3, -beta, -alpha, true, tt);
+        let null_score = -minimax(board, depth - 3, -beta, -alpha, true, tt, ctx)?;
         unsafe { board.undo_null_move(); }
         if null_score >= beta {
-            tt.store(hash, depth, null_score, 2);
-            return null_score;
+            tt.store(hash, depth, null_score, 2, BitMove::null());
+            return Ok(null_score);
         }
     }
 
@@ -408,40 +914,35 @@ pub fn minimax(board: &mut Board, depth: u8, mut alpha: i32, mut beta: i32, maxi
         .filter(|&&m| board.legal_move(m))
         .cloned()
         .collect();
-    order_moves(&mut moves_vec, board);
+    let ply = board.ply() as usize % MAX_PLY;
+    order_moves(&mut moves_vec, board, probe.best_move, ctx, ply);
 
     let mut score;
     let mut flag = 0u8;
+    let mut best_move = BitMove::null();
     if maximizing {
         score = -INFINITY;
         for m in moves_vec {
             let moved_pt = board.moved_piece(m).type_of();
             board.apply_move(m);
-            let eval = minimax(board, depth - 1, alpha, beta, false, tt);
+            ctx.history.push(board.zobrist());
+            let eval = minimax(board, depth - 1, alpha, beta, false, tt, ctx);
+            ctx.history.pop();
             board.undo_move();
-            score = score.max(eval);
+            let eval = eval?;
+            if eval > score {
+                score = eval;
+                best_move = m;
+            }
             alpha = alpha.max(eval);
             if beta <= alpha {
                 flag = 1;
-                unsafe {
-                    let ply = board.ply() as usize % MAX_PLY;
-                    if KILLERS[ply][0].is_null() {
-                        KILLERS[ply][0] = m;
-                    } else if KILLERS[ply][1].is_null() {
-                        KILLERS[ply][1] = m;
-                    }
-                    let pt_idx = match moved_pt {
-                        PieceType::P => 0,
-                        PieceType::N => 1,
-                        PieceType::B => 2,
-                        PieceType::R => 3,
-                        PieceType::Q => 4,
-                        PieceType::K => 5,
-                        _ => 0,
-                    };
-                    let sq = m.get_dest();
-                    HISTORY[pt_idx][sq.0 as usize] += depth as i32 * depth as i32;
+                if ctx.killers[ply][0].is_null() {
+                    ctx.killers[ply][0] = m;
+                } else if ctx.killers[ply][1].is_null() {
+                    ctx.killers[ply][1] = m;
                 }
+                ctx.history_heuristic[piece_index(moved_pt)][m.get_dest().0 as usize] += depth as i32 * depth as i32;
                 break;
             }
         }
@@ -450,49 +951,49 @@ pub fn minimax(board: &mut Board, depth: u8, mut alpha: i32, mut beta: i32, maxi
         for m in moves_vec {
             let moved_pt = board.moved_piece(m).type_of();
             board.apply_move(m);
-            let eval = minimax(board, depth - 1, alpha, beta, true, tt);
+            ctx.history.push(board.zobrist());
+            let eval = minimax(board, depth - 1, alpha, beta, true, tt, ctx);
+            ctx.history.pop();
             board.undo_move();
-            score = score.min(eval);
+            let eval = eval?;
+            if eval < score {
+                score = eval;
+                best_move = m;
+            }
             beta = beta.min(eval);
             if beta <= alpha {
                 flag = 2;
-                unsafe {
-                    let ply = board.ply() as usize % MAX_PLY;
-                    if KILLERS[ply][0].is_null() {
-                        KILLERS[ply][0] = m;
-                    } else if KILLERS[ply][1].is_null() {
-                        KILLERS[ply][1] = m;
-                    }
-                    let pt_idx = match moved_pt {
-                        PieceType::P => 0,
-                        PieceType::N => 1,
-                        PieceType::B => 2,
-                        PieceType::R => 3,
-                        PieceType::Q => 4,
-                        PieceType::K => 5,
-                        _ => 0,
-                    };
-                    let sq = m.get_dest();
-                    HISTORY[pt_idx][sq.0 as usize] += depth as i32 * depth as i32;
+                if ctx.killers[ply][0].is_null() {
+                    ctx.killers[ply][0] = m;
+                } else if ctx.killers[ply][1].is_null() {
+                    ctx.killers[ply][1] = m;
                 }
+                ctx.history_heuristic[piece_index(moved_pt)][m.get_dest().0 as usize] += depth as i32 * depth as i32;
                 break;
             }
         }
     }
 
-    tt.store(hash, depth, score, flag);
-    score
+    tt.store(hash, depth, score, flag, best_move);
+    Ok(score)
 }
 
-fn quiescence(board: &mut Board, mut alpha: i32, beta: i32, tt: &mut CustomTT) -> i32 {
+fn quiescence(board: &mut Board, mut alpha: i32, beta: i32, tt: &CustomTT, ctx: &mut SearchContext) -> Result<i32, SearchTimeout> {
+    ctx.tick()?;
+
     let hash = board.zobrist();
-    if let Some(score) = tt.probe(hash, 0, alpha, beta) {
-        return score;
+
+    if board.rule_50() >= 100 || is_repeated_position(&ctx.history, board.rule_50() as u16) {
+        return Ok(0);
+    }
+
+    if let Some(score) = tt.probe(hash, 0, alpha, beta).score {
+        return Ok(score);
     }
 
     let stand_pat = evaluate_board(board);
     if stand_pat >= beta {
-        return beta;
+        return Ok(beta);
     }
     if alpha < stand_pat {
         alpha = stand_pat;
@@ -504,23 +1005,27 @@ fn quiescence(board: &mut Board, mut alpha: i32, beta: i32, tt: &mut CustomTT) -
         .filter(|&&m| board.is_capture(m) && board.legal_move(m))
         .cloned()
         .collect();
-    order_moves(&mut moves_vec, board);
+    let ply = board.ply() as usize % MAX_PLY;
+    order_moves(&mut moves_vec, board, None, ctx, ply);
 
     let mut score = stand_pat;
     for m in moves_vec {
         if !board.see_ge(m, 0) { continue; }
         board.apply_move(m);
-        let eval = -quiescence(board, -beta, -alpha, tt);
+        ctx.history.push(board.zobrist());
+        let eval = quiescence(board, -beta, -alpha, tt, ctx);
+        ctx.history.pop();
         board.undo_move();
+        let eval = -eval?;
         if eval >= beta {
-            tt.store(hash, 0, beta, 2);
-            return beta;
+            tt.store(hash, 0, beta, 2, m);
+            return Ok(beta);
         }
         if eval > alpha {
             alpha = eval;
         }
         score = score.max(eval);
     }
-    tt.store(hash, 0, alpha, 0);
-    alpha
+    tt.store(hash, 0, alpha, 0, BitMove::null());
+    Ok(alpha)
 }
@@ -1,4 +1,5 @@
 mod engine;
+mod uci;
 
 use pleco::{Board, Player};
 use std::fs;
@@ -55,7 +56,14 @@ fn parse_san(board: &Board, san: &str) -> Result<pleco::BitMove, String> {
 }
 
 fn main() {
+    if std::env::args().any(|a| a == "uci") {
+        uci::run();
+        return;
+    }
+
     let mut board = Board::start_pos();
+    let mut history: Vec<u64> = vec![board.zobrist()];
+    let tt = engine::CustomTT::new();
     println!("Starting game:\n{:?}", board);
 
     // Read openings from CSV
@@ -79,6 +87,7 @@ fn main() {
             match parse_san(&board, san_move.as_str()) {
                 Ok(mv) => {
                     board.apply_move(mv);
+                    history.push(board.zobrist());
                     println!("Applied move: {} - Board: {:?}", san_move, board);
                 }
                 Err(e) => {
@@ -90,13 +99,20 @@ fn main() {
         println!("Opening complete. Board after opening:\n{:?}", board);
     }
 
+    let mut drawn_by_repetition = false;
     while !board.checkmate() && !board.stalemate() {
-        let best_move = engine::get_best_move(&mut board, engine::MAX_DEPTH);
+        if engine::has_occurred_twice(&history, board.zobrist()) {
+            drawn_by_repetition = true;
+            break;
+        }
+
+        let best_move = engine::get_best_move(&mut board, engine::MAX_DEPTH, &history, &tt);
         if best_move.is_null() {
             break;
         }
         println!("Best move: {:?} (turn: {:?})", best_move, board.turn());
         board.apply_move(best_move);
+        history.push(board.zobrist());
         println!("Board after move:\n{:?}", board);
     }
 
@@ -105,6 +121,8 @@ fn main() {
         println!("Checkmate! {} wins.", winner);
     } else if board.stalemate() {
         println!("Stalemate! It's a draw.");
+    } else if drawn_by_repetition {
+        println!("Draw by repetition.");
     } else {
         println!("Game ended.");
     }
@@ -0,0 +1,207 @@
+use crate::engine;
+use pleco::{Board, Player};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Runs the UCI command loop on stdin/stdout, driving the engine the way a
+/// chess GUI or lichess-bot would instead of the CSV self-play harness.
+///
+/// `go` runs on its own thread so the loop keeps reading stdin (and can
+/// answer `isready`, or flip `stop`) while a search is in flight; `stop`
+/// sets the shared flag the running search's `SearchContext` polls, and the
+/// search thread itself prints the final `bestmove` once it unwinds.
+pub fn run() {
+    let stdin = io::stdin();
+    let mut board = Board::start_pos();
+    let mut history: Vec<u64> = vec![board.zobrist()];
+    let mut threads: usize = 1;
+    let mut search: Option<(Arc<AtomicBool>, JoinHandle<()>)> = None;
+    let tt = Arc::new(engine::CustomTT::new());
+
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(_) => break,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name Zenith");
+                println!("id author Duytan1801");
+                println!("option name Threads type spin default 1 min 1 max 64");
+                println!("uciok");
+                io::stdout().flush().ok();
+            }
+            Some("isready") => {
+                println!("readyok");
+                io::stdout().flush().ok();
+            }
+            Some("ucinewgame") => {
+                stop_search(&mut search);
+                board = Board::start_pos();
+                history = vec![board.zobrist()];
+            }
+            Some("setoption") => handle_setoption(&mut threads, tokens),
+            Some("position") => {
+                stop_search(&mut search);
+                handle_position(&mut board, &mut history, tokens);
+            }
+            Some("go") => {
+                stop_search(&mut search);
+                search = Some(handle_go(&board, &history, threads, &tt, tokens));
+            }
+            Some("stop") => stop_search(&mut search),
+            Some("quit") => {
+                stop_search(&mut search);
+                break;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Signals a running search to stop and waits for its thread to finish
+/// printing `bestmove`, if one is in flight. A no-op otherwise.
+fn stop_search(search: &mut Option<(Arc<AtomicBool>, JoinHandle<()>)>) {
+    if let Some((stop, handle)) = search.take() {
+        stop.store(true, Ordering::Relaxed);
+        handle.join().ok();
+    }
+}
+
+/// Parses `setoption name <id> value <x>`. `Threads` is the only option
+/// exposed today (see the `option` line in the `uci` response above).
+fn handle_setoption<'a>(threads: &mut usize, mut tokens: impl Iterator<Item = &'a str>) {
+    if tokens.next() != Some("name") {
+        return;
+    }
+    let name_parts: Vec<&str> = tokens.by_ref().take_while(|&t| t != "value").collect();
+    let name = name_parts.join(" ");
+    let value = tokens.next();
+
+    if name.eq_ignore_ascii_case("Threads") {
+        if let Some(n) = value.and_then(|v| v.parse::<usize>().ok()) {
+            *threads = n.clamp(1, 64);
+        }
+    }
+}
+
+/// A `position` command resends the full move list from the game's start
+/// every time, so the repetition history is rebuilt from scratch alongside
+/// the board rather than incrementally maintained.
+fn handle_position<'a>(board: &mut Board, history: &mut Vec<u64>, mut tokens: impl Iterator<Item = &'a str>) {
+    match tokens.next() {
+        Some("startpos") => {
+            *board = Board::start_pos();
+        }
+        Some("fen") => {
+            let fen_parts: Vec<&str> = tokens.by_ref().take_while(|&t| t != "moves").collect();
+            let fen = fen_parts.join(" ");
+            *board = Board::from_fen(&fen).unwrap_or_else(|_| Board::start_pos());
+        }
+        _ => return,
+    }
+    *history = vec![board.zobrist()];
+
+    for tok in tokens {
+        if tok == "moves" {
+            continue;
+        }
+        match engine::uci_to_bitmove(board, tok) {
+            Ok(mv) => {
+                board.apply_move(mv);
+                history.push(board.zobrist());
+            }
+            Err(_) => break,
+        }
+    }
+}
+
+/// Parses `go`'s sub-args and spawns the search on its own thread so the
+/// main loop stays free to answer `isready`/`stop` while it runs. Returns
+/// the shared stop flag (for a later `stop`) and the thread's `JoinHandle`;
+/// the thread itself prints `bestmove` once the search returns.
+fn handle_go<'a>(
+    board: &Board,
+    history: &[u64],
+    threads: usize,
+    tt: &Arc<engine::CustomTT>,
+    tokens: impl Iterator<Item = &'a str>,
+) -> (Arc<AtomicBool>, JoinHandle<()>) {
+    let mut depth = engine::MAX_DEPTH;
+    let mut depth_specified = false;
+    let mut movetime: Option<u64> = None;
+    let mut wtime: Option<u64> = None;
+    let mut btime: Option<u64> = None;
+    let mut winc: Option<u64> = None;
+    let mut binc: Option<u64> = None;
+    let mut infinite = false;
+
+    let mut tokens = tokens.peekable();
+    while let Some(tok) = tokens.next() {
+        match tok {
+            "depth" => {
+                if let Some(d) = tokens.next().and_then(|s| s.parse().ok()) {
+                    depth = d;
+                    depth_specified = true;
+                }
+            }
+            "movetime" => movetime = tokens.next().and_then(|s| s.parse().ok()),
+            "wtime" => wtime = tokens.next().and_then(|s| s.parse().ok()),
+            "btime" => btime = tokens.next().and_then(|s| s.parse().ok()),
+            "winc" => winc = tokens.next().and_then(|s| s.parse().ok()),
+            "binc" => binc = tokens.next().and_then(|s| s.parse().ok()),
+            "infinite" => infinite = true,
+            "ponder" => {}
+            _ => {}
+        }
+    }
+
+    // The clock's worth of time is only meaningful for the side to move;
+    // no clock info (or `infinite`) means search to `depth` uncapped, bounded
+    // only by `stop`.
+    let deadline = if infinite {
+        None
+    } else if let Some(ms) = movetime {
+        Some(Instant::now() + Duration::from_millis(ms))
+    } else {
+        let (time_left, increment) = if board.turn() == Player::White {
+            (wtime, winc.unwrap_or(0))
+        } else {
+            (btime, binc.unwrap_or(0))
+        };
+        time_left.map(|ms| Instant::now() + Duration::from_millis(engine::time_budget_ms(ms, increment)))
+    };
+
+    // A time budget (or `infinite`, bounded only by `stop`) should be the
+    // thing that stops iterative deepening, not `MAX_DEPTH` - otherwise
+    // depth 5 finishes in milliseconds, the deadline/branching-factor logic
+    // never gets a chance to matter, and `go infinite` reports `bestmove` on
+    // its own instead of waiting for `stop` as the UCI protocol requires. An
+    // explicit `go depth N` still wins since the user asked for that depth.
+    if !depth_specified && (infinite || movetime.is_some() || wtime.is_some() || btime.is_some()) {
+        depth = u8::MAX;
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let mut board = board.shallow_clone();
+    let history = history.to_vec();
+    let tt = tt.clone();
+
+    let handle = std::thread::spawn(move || {
+        let best_move = engine::get_best_move_timed(&mut board, depth, deadline, thread_stop, &history, threads, &tt);
+        println!("bestmove {}", engine::bitmove_to_uci(best_move));
+        io::stdout().flush().ok();
+    });
+
+    (stop, handle)
+}